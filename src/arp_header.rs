@@ -0,0 +1,118 @@
+use super::*;
+
+///Minimum number of bytes/octets the fixed part of an ARP packet (RFC 826)
+///takes up, before the variable length address fields.
+pub const ARP_FIXED_HEADER_LEN: usize = 8;
+
+///ARP (Address Resolution Protocol) packet. Hardware & protocol addresses are
+///kept as raw byte slices, since their length is only known at parse time
+///(given by `hardware_addr_len`/`protocol_addr_len`).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ArpHeader<'a> {
+    pub hardware_type: u16,
+    pub protocol_type: u16,
+    pub hardware_addr_len: u8,
+    pub protocol_addr_len: u8,
+    pub operation: u16,
+    pub sender_hardware_addr: &'a [u8],
+    pub sender_protocol_addr: &'a [u8],
+    pub target_hardware_addr: &'a [u8],
+    pub target_protocol_addr: &'a [u8],
+}
+
+impl<'a> ArpHeader<'a> {
+    ///Reads an ARP packet from a slice. The passed slice has to start with
+    ///the first byte of the ARP packet (i.e. directly after the ethernet header).
+    pub fn read_from_slice(slice: &'a [u8]) -> Result<(ArpHeader<'a>, &'a [u8]), ReadError> {
+        if slice.len() < ARP_FIXED_HEADER_LEN {
+            return Err(ReadError::UnexpectedEndOfSlice(ARP_FIXED_HEADER_LEN));
+        }
+
+        let hardware_type = u16::from_be_bytes([slice[0], slice[1]]);
+        let protocol_type = u16::from_be_bytes([slice[2], slice[3]]);
+        let hardware_addr_len = slice[4];
+        let protocol_addr_len = slice[5];
+        let operation = u16::from_be_bytes([slice[6], slice[7]]);
+
+        let hal = usize::from(hardware_addr_len);
+        let pal = usize::from(protocol_addr_len);
+        let total_len = ARP_FIXED_HEADER_LEN + 2*hal + 2*pal;
+        if slice.len() < total_len {
+            return Err(ReadError::UnexpectedEndOfSlice(total_len));
+        }
+
+        let mut rest = &slice[ARP_FIXED_HEADER_LEN..];
+        let (sender_hardware_addr, new_rest) = rest.split_at(hal);
+        rest = new_rest;
+        let (sender_protocol_addr, new_rest) = rest.split_at(pal);
+        rest = new_rest;
+        let (target_hardware_addr, new_rest) = rest.split_at(hal);
+        rest = new_rest;
+        let (target_protocol_addr, new_rest) = rest.split_at(pal);
+        rest = new_rest;
+
+        Ok((
+            ArpHeader{
+                hardware_type,
+                protocol_type,
+                hardware_addr_len,
+                protocol_addr_len,
+                operation,
+                sender_hardware_addr,
+                sender_protocol_addr,
+                target_hardware_addr,
+                target_protocol_addr,
+            },
+            rest,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_an_ethernet_ipv4_arp_request() {
+        let bytes = [
+            0x00, 0x01, //hardware type = ethernet
+            0x08, 0x00, //protocol type = ipv4
+            6, 4, //hardware/protocol address length
+            0x00, 0x01, //operation = request
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, //sender hardware addr
+            192, 168, 0, 1, //sender protocol addr
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //target hardware addr
+            192, 168, 0, 2, //target protocol addr
+            9, 9, //trailing bytes, not part of the packet
+        ];
+        let (header, rest) = ArpHeader::read_from_slice(&bytes).unwrap();
+        assert_eq!(header.hardware_type, 1);
+        assert_eq!(header.protocol_type, 0x0800);
+        assert_eq!(header.hardware_addr_len, 6);
+        assert_eq!(header.protocol_addr_len, 4);
+        assert_eq!(header.operation, 1);
+        assert_eq!(header.sender_hardware_addr, &[0x11, 0x22, 0x33, 0x44, 0x55, 0x66]);
+        assert_eq!(header.sender_protocol_addr, &[192, 168, 0, 1]);
+        assert_eq!(header.target_hardware_addr, &[0, 0, 0, 0, 0, 0]);
+        assert_eq!(header.target_protocol_addr, &[192, 168, 0, 2]);
+        assert_eq!(rest, &[9, 9]);
+    }
+
+    #[test]
+    fn read_from_slice_errors_on_short_fixed_header() {
+        let bytes = [0x00, 0x01, 0x08, 0x00, 6, 4, 0x00];
+        assert_eq!(
+            ArpHeader::read_from_slice(&bytes).unwrap_err(),
+            ReadError::UnexpectedEndOfSlice(ARP_FIXED_HEADER_LEN)
+        );
+    }
+
+    #[test]
+    fn read_from_slice_errors_on_short_addresses() {
+        let bytes = [0x00, 0x01, 0x08, 0x00, 6, 4, 0x00, 0x01, 0, 0, 0];
+        assert_eq!(
+            ArpHeader::read_from_slice(&bytes).unwrap_err(),
+            ReadError::UnexpectedEndOfSlice(ARP_FIXED_HEADER_LEN + 2*6 + 2*4)
+        );
+    }
+}
@@ -0,0 +1,324 @@
+use super::*;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+///Upper bound for an IPv4 fragment's `offset + payload.len()`, given by the
+///16 bit Total Length field. The 13 bit Fragment Offset (in 8-byte units)
+///reaches up to 65528, so a fragment can claim to end beyond this size and
+///is rejected by `FragmentBuffer::add` when it does.
+const IPV4_MAX_DATAGRAM_SIZE: usize = 65535;
+
+///Error returned by `FragmentBuffer::add` when a fragment cannot be stored.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FragmentError {
+    ///The fragment's offset + payload length would exceed the maximum
+    ///datagram size allowed for the IP version (65535 for IPv4) or the
+    ///`FragmentBuffer`'s configured `max_datagram_size`.
+    DatagramTooLarge{ offset_plus_len: usize, max: usize },
+}
+
+impl std::fmt::Display for FragmentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            FragmentError::DatagramTooLarge{ offset_plus_len, max } =>
+                write!(f, "FragmentError::DatagramTooLarge: fragment end {} exceeds the maximum allowed datagram size of {}", offset_plus_len, max),
+        }
+    }
+}
+
+impl std::error::Error for FragmentError {}
+
+///Identifies the datagram a fragment belongs to, as described in RFC 791
+///(source, destination, protocol, identification) resp. RFC 8200 for IPv6,
+///or by (link layer source/destination, datagram tag) for 6LoWPAN (RFC 4944).
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+enum FragmentKey {
+    V4{ source: [u8;4], destination: [u8;4], protocol: u8, identification: u16 },
+    V6{ source: [u8;16], destination: [u8;16], next_header: u8, identification: u32 },
+    SixLowPan{ source: Ieee802154Address, destination: Ieee802154Address, datagram_tag: u16 },
+}
+
+///A single contiguous byte range `[start, end)` that has already been
+///received for a partial datagram.
+type ByteRange = (usize, usize);
+
+struct PartialDatagram {
+    buffer: Vec<u8>,
+    received: Vec<ByteRange>,
+    ///Total datagram length, only known once the fragment with `more_fragments == false` arrives.
+    total_len: Option<usize>,
+    last_update: Instant,
+}
+
+impl PartialDatagram {
+    fn new() -> PartialDatagram {
+        PartialDatagram{
+            buffer: Vec::new(),
+            received: Vec::new(),
+            total_len: None,
+            last_update: Instant::now(),
+        }
+    }
+
+    ///Copies `payload` into `self.buffer` at `offset`, preferring already
+    ///received bytes over the new fragment (first-received wins, mitigating
+    ///overlapping-fragment attacks). `total_len`, if given, is the full size
+    ///of the reassembled datagram as known from this fragment (IPv4/IPv6
+    ///fragments only know it once the last fragment arrives; 6LoWPAN
+    ///fragments carry it on every fragment).
+    fn insert(&mut self, offset: usize, payload: &[u8], total_len: Option<usize>) {
+        self.last_update = Instant::now();
+
+        let end = offset + payload.len();
+        if self.buffer.len() < end {
+            self.buffer.resize(end, 0);
+        }
+
+        //only copy the parts of payload that do not overlap already received ranges
+        let mut pos = offset;
+        for &mut_byte in payload {
+            if !self.received.iter().any(|&(start, r_end)| pos >= start && pos < r_end) {
+                self.buffer[pos] = mut_byte;
+            }
+            pos += 1;
+        }
+
+        Self::add_range(&mut self.received, (offset, end));
+
+        if let Some(total_len) = total_len {
+            self.total_len = Some(total_len);
+        }
+    }
+
+    fn add_range(received: &mut Vec<ByteRange>, new_range: ByteRange) {
+        received.push(new_range);
+        received.sort_unstable_by_key(|r| r.0);
+
+        //merge overlapping/adjacent ranges
+        let mut merged: Vec<ByteRange> = Vec::with_capacity(received.len());
+        for &(start, end) in received.iter() {
+            if let Some(last) = merged.last_mut() {
+                if start <= last.1 {
+                    last.1 = last.1.max(end);
+                    continue;
+                }
+            }
+            merged.push((start, end));
+        }
+        *received = merged;
+    }
+
+    ///`true` once the final fragment has arrived and `[0, total_len)` is fully covered.
+    fn is_complete(&self) -> bool {
+        match self.total_len {
+            None => false,
+            Some(total_len) => {
+                self.received.len() == 1 && self.received[0] == (0, total_len)
+            },
+        }
+    }
+}
+
+///Reassembles IPv4, IPv6 and 6LoWPAN (FRAG1/FRAGN) fragments back into
+///their original payload.
+///
+///Fragments are fed in via [`FragmentBuffer::add`], which returns `None`
+///while the datagram they belong to is still incomplete and
+///`Some(payload)` once every byte range `[0, total_len)` has been received.
+///
+///To bound memory use against fragmentation based denial-of-service
+///attacks, a fragment that would extend its datagram beyond
+///`max_datagram_size` is rejected with `FragmentError::DatagramTooLarge`
+///(the partial datagram itself is kept), and partial datagrams that have
+///not seen a new fragment for `timeout` are dropped.
+pub struct FragmentBuffer {
+    parts: HashMap<FragmentKey, PartialDatagram>,
+    max_datagram_size: usize,
+    timeout: Duration,
+}
+
+impl FragmentBuffer {
+    pub fn new(max_datagram_size: usize, timeout: Duration) -> FragmentBuffer {
+        FragmentBuffer{
+            parts: HashMap::new(),
+            max_datagram_size,
+            timeout,
+        }
+    }
+
+    ///Feeds the fragment contained in the given (already parsed) `PacketHeaders`
+    ///into the reassembly buffer. Returns `Ok(None)` if `headers` is not a
+    ///fragment or the datagram it belongs to is still incomplete, and
+    ///`Ok(Some(payload))` once the full datagram has been reassembled.
+    pub fn add(&mut self, headers: &PacketHeaders) -> Result<Option<Vec<u8>>, FragmentError> {
+        self.evict_expired();
+
+        if let Some(sixlowpan_fragment) = &headers.sixlowpan_fragment {
+            return match &headers.link {
+                Some(LinkHeader::Ieee802154(mac)) => {
+                    let (datagram_size, datagram_tag, offset) = match sixlowpan_fragment {
+                        SixLowPanFragHeader::First(frag) => (frag.datagram_size, frag.datagram_tag, 0),
+                        SixLowPanFragHeader::Subsequent(frag) =>
+                            (frag.datagram_size, frag.datagram_tag, usize::from(frag.datagram_offset) * 8),
+                    };
+                    self.add_fragment(
+                        FragmentKey::SixLowPan{
+                            source: mac.src_address,
+                            destination: mac.dest_address,
+                            datagram_tag,
+                        },
+                        offset,
+                        headers.payload,
+                        //every 6LoWPAN fragment carries the full datagram size, unlike IPv4/IPv6
+                        Some(usize::from(datagram_size)),
+                        self.max_datagram_size,
+                    )
+                },
+                //a 6LoWPAN fragment without its 802.15.4 MAC header can't be keyed
+                _ => Ok(None),
+            };
+        }
+
+        match &headers.ip {
+            Some(IpHeader::Version4(ipv4)) => {
+                if !ipv4.more_fragments && 0 == ipv4.fragments_offset {
+                    //not a fragment at all
+                    return Ok(None);
+                }
+                let offset = usize::from(ipv4.fragments_offset) * 8;
+                let total_len = if ipv4.more_fragments { None } else { Some(offset + headers.payload.len()) };
+                self.add_fragment(
+                    FragmentKey::V4{
+                        source: ipv4.source,
+                        destination: ipv4.destination,
+                        protocol: ipv4.protocol,
+                        identification: ipv4.identification,
+                    },
+                    offset,
+                    headers.payload,
+                    total_len,
+                    IPV4_MAX_DATAGRAM_SIZE,
+                )
+            },
+            Some(IpHeader::Version6(ipv6)) => {
+                match headers.ipv6_extensions.as_ref().and_then(|ext| ext.fragment.as_ref()) {
+                    None => Ok(None),
+                    Some(fragment) => {
+                        let offset = usize::from(fragment.fragment_offset) * 8;
+                        let total_len = if fragment.more_fragments { None } else { Some(offset + headers.payload.len()) };
+                        self.add_fragment(
+                            FragmentKey::V6{
+                                source: ipv6.source,
+                                destination: ipv6.destination,
+                                next_header: fragment.next_header,
+                                identification: fragment.identification,
+                            },
+                            offset,
+                            headers.payload,
+                            total_len,
+                            self.max_datagram_size,
+                        )
+                    },
+                }
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn add_fragment(
+        &mut self,
+        key: FragmentKey,
+        offset: usize,
+        payload: &[u8],
+        total_len: Option<usize>,
+        max_size: usize,
+    ) -> Result<Option<Vec<u8>>, FragmentError> {
+        let end = offset + payload.len();
+        let max = self.max_datagram_size.min(max_size);
+        if end > max {
+            return Err(FragmentError::DatagramTooLarge{ offset_plus_len: end, max });
+        }
+
+        let datagram = self.parts.entry(key).or_insert_with(PartialDatagram::new);
+        datagram.insert(offset, payload, total_len);
+
+        if datagram.is_complete() {
+            let datagram = self.parts.remove(&key).unwrap();
+            Ok(Some(datagram.buffer))
+        } else {
+            Ok(None)
+        }
+    }
+
+    ///Drops all partial datagrams that have not received a new fragment within `timeout`.
+    fn evict_expired(&mut self) {
+        let timeout = self.timeout;
+        self.parts.retain(|_, datagram| datagram.last_update.elapsed() < timeout);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    ///Builds a minimal 20 byte IPv4 header (no options) around `payload`,
+    ///fragmented according to `more_fragments`/`fragments_offset_units` (in
+    ///8-byte units, as carried on the wire).
+    fn ipv4_fragment(
+        identification: u16,
+        more_fragments: bool,
+        fragments_offset_units: u16,
+        protocol: u8,
+        payload: &[u8],
+    ) -> Vec<u8> {
+        let mut packet = vec![0u8; 20 + payload.len()];
+        packet[0] = 0x45; // version 4, IHL 5
+        packet[2..4].copy_from_slice(&((20 + payload.len()) as u16).to_be_bytes());
+        packet[4..6].copy_from_slice(&identification.to_be_bytes());
+        let flags_and_offset = (u16::from(more_fragments) << 13) | fragments_offset_units;
+        packet[6..8].copy_from_slice(&flags_and_offset.to_be_bytes());
+        packet[8] = 64; // ttl
+        packet[9] = protocol;
+        packet[12..16].copy_from_slice(&[192, 168, 1, 1]);
+        packet[16..20].copy_from_slice(&[192, 168, 1, 2]);
+        packet[20..].copy_from_slice(payload);
+        packet
+    }
+
+    #[test]
+    fn reassembles_a_split_udp_datagram() {
+        //a whole (unfragmented) UDP datagram: 8 byte header + 10 byte payload
+        let udp_header = [0x30, 0x39, 0x00, 0x50, 0x00, 18, 0x00, 0x00];
+        let udp_payload = b"HELLOWORLD";
+        let mut whole = Vec::new();
+        whole.extend_from_slice(&udp_header);
+        whole.extend_from_slice(udp_payload);
+
+        //split at an 8 byte boundary: fragment 1 carries just the UDP header,
+        //fragment 2 carries the UDP payload
+        let fragment1 = ipv4_fragment(1234, true, 0, 17, &whole[..8]);
+        let fragment2 = ipv4_fragment(1234, false, 1, 17, &whole[8..]);
+
+        let mut buffer = FragmentBuffer::new(65535, Duration::from_secs(30));
+
+        let headers1 = PacketHeaders::from_ip_slice(&fragment1).unwrap();
+        assert!(headers1.transport.is_none());
+        assert_eq!(headers1.payload, &whole[..8]);
+        assert_eq!(None, buffer.add(&headers1).unwrap());
+
+        let headers2 = PacketHeaders::from_ip_slice(&fragment2).unwrap();
+        assert!(headers2.transport.is_none());
+        assert_eq!(headers2.payload, &whole[8..]);
+        let reassembled = buffer.add(&headers2).unwrap();
+
+        assert_eq!(Some(whole), reassembled);
+    }
+
+    #[test]
+    fn non_fragmented_packets_are_ignored() {
+        let mut buffer = FragmentBuffer::new(65535, Duration::from_secs(30));
+        let packet = ipv4_fragment(1, false, 0, 17, &[1, 2, 3, 4]);
+        let headers = PacketHeaders::from_ip_slice(&packet).unwrap();
+        assert_eq!(None, buffer.add(&headers).unwrap());
+    }
+}
@@ -0,0 +1,158 @@
+use super::*;
+
+///Minimum number of bytes/octets an ICMPv4 header takes up (type, code, checksum
+///and the 4 byte "rest of header" field).
+pub const ICMPV4_HEADER_LEN: usize = 8;
+
+///ICMPv4 type & "rest of header" combinations that are understood well enough to
+///be decoded into their own fields. Everything else is kept as raw bytes in
+///`Icmpv4Type::Other`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Icmpv4Type {
+    ///Echo Reply (type 0). `id` & `seq` come from the "rest of header" field.
+    EchoReply{ id: u16, seq: u16 },
+    ///Destination Unreachable (type 3). `code` carries the unreachable reason.
+    DestinationUnreachable{ code: u8 },
+    ///Echo Request (type 8). `id` & `seq` come from the "rest of header" field.
+    EchoRequest{ id: u16, seq: u16 },
+    ///Time Exceeded (type 11). `code` distinguishes TTL exceeded from
+    ///fragment reassembly time exceeded.
+    TimeExceeded{ code: u8 },
+    ///Any other type/code combination. The raw "rest of header" bytes are kept
+    ///as-is so callers can still interpret them if they know the type.
+    Other{ type_u8: u8, code_u8: u8, rest_of_header: [u8;4] },
+}
+
+impl Icmpv4Type {
+    fn from_bytes(type_u8: u8, code_u8: u8, rest_of_header: [u8;4]) -> Icmpv4Type {
+        use self::Icmpv4Type::*;
+        match (type_u8, code_u8) {
+            (0, 0) => EchoReply{
+                id: u16::from_be_bytes([rest_of_header[0], rest_of_header[1]]),
+                seq: u16::from_be_bytes([rest_of_header[2], rest_of_header[3]]),
+            },
+            (3, code) => DestinationUnreachable{ code },
+            (8, 0) => EchoRequest{
+                id: u16::from_be_bytes([rest_of_header[0], rest_of_header[1]]),
+                seq: u16::from_be_bytes([rest_of_header[2], rest_of_header[3]]),
+            },
+            (11, code) => TimeExceeded{ code },
+            (type_u8, code_u8) => Other{ type_u8, code_u8, rest_of_header },
+        }
+    }
+
+    fn to_bytes(&self) -> (u8, u8, [u8;4]) {
+        use self::Icmpv4Type::*;
+        match self {
+            EchoReply{ id, seq } => (0, 0, Self::id_seq_bytes(*id, *seq)),
+            DestinationUnreachable{ code } => (3, *code, [0;4]),
+            EchoRequest{ id, seq } => (8, 0, Self::id_seq_bytes(*id, *seq)),
+            TimeExceeded{ code } => (11, *code, [0;4]),
+            Other{ type_u8, code_u8, rest_of_header } => (*type_u8, *code_u8, *rest_of_header),
+        }
+    }
+
+    fn id_seq_bytes(id: u16, seq: u16) -> [u8;4] {
+        let id = id.to_be_bytes();
+        let seq = seq.to_be_bytes();
+        [id[0], id[1], seq[0], seq[1]]
+    }
+}
+
+///ICMPv4 header (RFC 792) as used by echo request/reply ("ping"), destination
+///unreachable, time exceeded and other control messages.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Icmpv4Header {
+    pub icmp_type: Icmpv4Type,
+    pub checksum: u16,
+}
+
+impl Icmpv4Header {
+    ///Reads an ICMPv4 header from a slice. The passed slice has to start with
+    ///the first byte of the ICMPv4 header.
+    pub fn read_from_slice(slice: &[u8]) -> Result<(Icmpv4Header, &[u8]), ReadError> {
+        if slice.len() < ICMPV4_HEADER_LEN {
+            return Err(ReadError::UnexpectedEndOfSlice(ICMPV4_HEADER_LEN));
+        }
+
+        let icmp_type = Icmpv4Type::from_bytes(
+            slice[0],
+            slice[1],
+            [slice[4], slice[5], slice[6], slice[7]],
+        );
+        let checksum = u16::from_be_bytes([slice[2], slice[3]]);
+
+        Ok((
+            Icmpv4Header{ icmp_type, checksum },
+            &slice[ICMPV4_HEADER_LEN..],
+        ))
+    }
+
+    ///Calculates the ICMPv4 checksum for the given payload (the internet
+    ///checksum over the ICMP header with the checksum field set to zero,
+    ///followed by the payload).
+    pub fn calc_checksum(&self, payload: &[u8]) -> u16 {
+        let (type_u8, code_u8, rest_of_header) = self.icmp_type.to_bytes();
+        checksum::Sum16BitWords::new()
+            .add_2bytes([type_u8, code_u8])
+            .add_4bytes(rest_of_header)
+            .add_slice(payload)
+            .ones_complement()
+            .to_be()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_echo_request() {
+        let bytes = [8, 0, 0xab, 0xcd, 0x01, 0x02, 0x00, 0x2a, 1, 2, 3];
+        let (header, rest) = Icmpv4Header::read_from_slice(&bytes).unwrap();
+        assert_eq!(header.icmp_type, Icmpv4Type::EchoRequest{ id: 0x0102, seq: 0x002a });
+        assert_eq!(header.checksum, 0xabcd);
+        assert_eq!(rest, &bytes[8..]);
+    }
+
+    #[test]
+    fn reads_destination_unreachable() {
+        let bytes = [3, 1, 0, 0, 0, 0, 0, 0];
+        let (header, rest) = Icmpv4Header::read_from_slice(&bytes).unwrap();
+        assert_eq!(header.icmp_type, Icmpv4Type::DestinationUnreachable{ code: 1 });
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn read_from_slice_errors_on_short_input() {
+        let bytes = [8, 0, 0, 0];
+        assert_eq!(
+            Icmpv4Header::read_from_slice(&bytes).unwrap_err(),
+            ReadError::UnexpectedEndOfSlice(ICMPV4_HEADER_LEN)
+        );
+    }
+
+    #[test]
+    fn calc_checksum_matches_the_internet_checksum() {
+        let header = Icmpv4Header{
+            icmp_type: Icmpv4Type::EchoRequest{ id: 1, seq: 2 },
+            checksum: 0,
+        };
+        //type=8, code=0, id=1, seq=2, payload=[0,0] -> words 0x0800,0x0001,0x0002,0x0000
+        assert_eq!(header.calc_checksum(&[0, 0]), 0xf7fc);
+    }
+
+    #[test]
+    fn type_bytes_round_trip() {
+        for icmp_type in [
+            Icmpv4Type::EchoRequest{ id: 1, seq: 2 },
+            Icmpv4Type::EchoReply{ id: 0xffff, seq: 0 },
+            Icmpv4Type::DestinationUnreachable{ code: 4 },
+            Icmpv4Type::TimeExceeded{ code: 0 },
+            Icmpv4Type::Other{ type_u8: 200, code_u8: 9, rest_of_header: [1,2,3,4] },
+        ] {
+            let (type_u8, code_u8, rest_of_header) = icmp_type.to_bytes();
+            assert_eq!(Icmpv4Type::from_bytes(type_u8, code_u8, rest_of_header), icmp_type);
+        }
+    }
+}
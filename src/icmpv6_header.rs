@@ -0,0 +1,167 @@
+use super::*;
+
+///Minimum number of bytes/octets an ICMPv6 header takes up (type, code, checksum
+///and the 4 byte "rest of header" field).
+pub const ICMPV6_HEADER_LEN: usize = 8;
+
+///ICMPv6 type & "rest of header" combinations that are understood well enough to
+///be decoded into their own fields (RFC 4443). Everything else is kept as raw
+///bytes in `Icmpv6Type::Other`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Icmpv6Type {
+    ///Destination Unreachable (type 1).
+    DestinationUnreachable{ code: u8 },
+    ///Time Exceeded (type 3).
+    TimeExceeded{ code: u8 },
+    ///Echo Request (type 128). `id` & `seq` come from the "rest of header" field.
+    EchoRequest{ id: u16, seq: u16 },
+    ///Echo Reply (type 129). `id` & `seq` come from the "rest of header" field.
+    EchoReply{ id: u16, seq: u16 },
+    ///Any other type/code combination.
+    Other{ type_u8: u8, code_u8: u8, rest_of_header: [u8;4] },
+}
+
+impl Icmpv6Type {
+    fn from_bytes(type_u8: u8, code_u8: u8, rest_of_header: [u8;4]) -> Icmpv6Type {
+        use self::Icmpv6Type::*;
+        match (type_u8, code_u8) {
+            (1, code) => DestinationUnreachable{ code },
+            (3, code) => TimeExceeded{ code },
+            (128, 0) => EchoRequest{
+                id: u16::from_be_bytes([rest_of_header[0], rest_of_header[1]]),
+                seq: u16::from_be_bytes([rest_of_header[2], rest_of_header[3]]),
+            },
+            (129, 0) => EchoReply{
+                id: u16::from_be_bytes([rest_of_header[0], rest_of_header[1]]),
+                seq: u16::from_be_bytes([rest_of_header[2], rest_of_header[3]]),
+            },
+            (type_u8, code_u8) => Other{ type_u8, code_u8, rest_of_header },
+        }
+    }
+
+    fn to_bytes(&self) -> (u8, u8, [u8;4]) {
+        use self::Icmpv6Type::*;
+        match self {
+            DestinationUnreachable{ code } => (1, *code, [0;4]),
+            TimeExceeded{ code } => (3, *code, [0;4]),
+            EchoRequest{ id, seq } => (128, 0, Self::id_seq_bytes(*id, *seq)),
+            EchoReply{ id, seq } => (129, 0, Self::id_seq_bytes(*id, *seq)),
+            Other{ type_u8, code_u8, rest_of_header } => (*type_u8, *code_u8, *rest_of_header),
+        }
+    }
+
+    fn id_seq_bytes(id: u16, seq: u16) -> [u8;4] {
+        let id = id.to_be_bytes();
+        let seq = seq.to_be_bytes();
+        [id[0], id[1], seq[0], seq[1]]
+    }
+}
+
+///ICMPv6 header (RFC 4443) as used by echo request/reply ("ping6"), destination
+///unreachable, time exceeded and other control messages.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Icmpv6Header {
+    pub icmp_type: Icmpv6Type,
+    pub checksum: u16,
+}
+
+impl Icmpv6Header {
+    ///Reads an ICMPv6 header from a slice. The passed slice has to start with
+    ///the first byte of the ICMPv6 header.
+    pub fn read_from_slice(slice: &[u8]) -> Result<(Icmpv6Header, &[u8]), ReadError> {
+        if slice.len() < ICMPV6_HEADER_LEN {
+            return Err(ReadError::UnexpectedEndOfSlice(ICMPV6_HEADER_LEN));
+        }
+
+        let icmp_type = Icmpv6Type::from_bytes(
+            slice[0],
+            slice[1],
+            [slice[4], slice[5], slice[6], slice[7]],
+        );
+        let checksum = u16::from_be_bytes([slice[2], slice[3]]);
+
+        Ok((
+            Icmpv6Header{ icmp_type, checksum },
+            &slice[ICMPV6_HEADER_LEN..],
+        ))
+    }
+
+    ///Calculates the ICMPv6 checksum for the given payload & IPv6 pseudo-header
+    ///(source/destination address + upper layer length + next header, per
+    ///RFC 2460 section 8.1, with next header fixed to 58/ICMPv6).
+    pub fn calc_checksum(&self, source: [u8;16], destination: [u8;16], payload: &[u8]) -> Result<u16, ValueError> {
+        let (type_u8, code_u8, rest_of_header) = self.icmp_type.to_bytes();
+        let icmp_len = ICMPV6_HEADER_LEN + payload.len();
+        if icmp_len > u32::MAX as usize {
+            return Err(ValueError::Ipv6PayloadLengthTooLarge(icmp_len));
+        }
+        Ok(checksum::Sum16BitWords::new()
+            .add_16bytes(source)
+            .add_16bytes(destination)
+            .add_4bytes((icmp_len as u32).to_be_bytes())
+            .add_2bytes([0, IpTrafficClass::Ipv6Icmp as u8])
+            .add_2bytes([type_u8, code_u8])
+            .add_4bytes(rest_of_header)
+            .add_slice(payload)
+            .ones_complement()
+            .to_be())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_echo_reply() {
+        let bytes = [129, 0, 0x12, 0x34, 0x00, 0x01, 0x00, 0x02, 9, 9];
+        let (header, rest) = Icmpv6Header::read_from_slice(&bytes).unwrap();
+        assert_eq!(header.icmp_type, Icmpv6Type::EchoReply{ id: 1, seq: 2 });
+        assert_eq!(header.checksum, 0x1234);
+        assert_eq!(rest, &bytes[8..]);
+    }
+
+    #[test]
+    fn reads_time_exceeded() {
+        let bytes = [3, 0, 0, 0, 0, 0, 0, 0];
+        let (header, _) = Icmpv6Header::read_from_slice(&bytes).unwrap();
+        assert_eq!(header.icmp_type, Icmpv6Type::TimeExceeded{ code: 0 });
+    }
+
+    #[test]
+    fn read_from_slice_errors_on_short_input() {
+        let bytes = [128, 0, 0, 0, 0];
+        assert_eq!(
+            Icmpv6Header::read_from_slice(&bytes).unwrap_err(),
+            ReadError::UnexpectedEndOfSlice(ICMPV6_HEADER_LEN)
+        );
+    }
+
+    #[test]
+    fn calc_checksum_matches_the_internet_checksum_including_pseudo_header() {
+        let header = Icmpv6Header{
+            icmp_type: Icmpv6Type::EchoRequest{ id: 1, seq: 2 },
+            checksum: 0,
+        };
+        //pseudo header of all-zero addresses + icmp_len=10 + next header 58, plus
+        //type=128, code=0, id=1, seq=2, payload=[0,0]
+        assert_eq!(
+            header.calc_checksum([0;16], [0;16], &[0, 0]).unwrap(),
+            0x7fb8
+        );
+    }
+
+    #[test]
+    fn type_bytes_round_trip() {
+        for icmp_type in [
+            Icmpv6Type::EchoRequest{ id: 7, seq: 8 },
+            Icmpv6Type::EchoReply{ id: 0, seq: 0xffff },
+            Icmpv6Type::DestinationUnreachable{ code: 3 },
+            Icmpv6Type::TimeExceeded{ code: 1 },
+            Icmpv6Type::Other{ type_u8: 200, code_u8: 1, rest_of_header: [9,8,7,6] },
+        ] {
+            let (type_u8, code_u8, rest_of_header) = icmp_type.to_bytes();
+            assert_eq!(Icmpv6Type::from_bytes(type_u8, code_u8, rest_of_header), icmp_type);
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use super::*;
+
+///The 3 bit "Frame Type" sub-field of the IEEE 802.15.4 Frame Control Field.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Ieee802154FrameType {
+    Beacon,
+    Data,
+    Acknowledgment,
+    MacCommand,
+    Reserved(u8),
+}
+
+impl Ieee802154FrameType {
+    fn from_u8(value: u8) -> Ieee802154FrameType {
+        use Ieee802154FrameType::*;
+        match value {
+            0 => Beacon,
+            1 => Data,
+            2 => Acknowledgment,
+            3 => MacCommand,
+            other => Reserved(other),
+        }
+    }
+}
+
+///The 2 bit addressing mode sub-fields of the Frame Control Field.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Ieee802154AddressingMode {
+    None,
+    Reserved,
+    Short,
+    Extended,
+}
+
+impl Ieee802154AddressingMode {
+    fn from_u8(value: u8) -> Ieee802154AddressingMode {
+        use Ieee802154AddressingMode::*;
+        match value {
+            0b00 => None,
+            0b01 => Reserved,
+            0b10 => Short,
+            _ => Extended,
+        }
+    }
+
+    ///Number of bytes an address encoded with this mode takes up, `0` if
+    ///there is no address at all.
+    fn len(&self) -> usize {
+        use Ieee802154AddressingMode::*;
+        match self {
+            None | Reserved => 0,
+            Short => 2,
+            Extended => 8,
+        }
+    }
+}
+
+///A source or destination IEEE 802.15.4 link layer address.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Ieee802154Address {
+    None,
+    Short([u8;2]),
+    Extended([u8;8]),
+}
+
+///IEEE 802.15.4-2006 MAC header, covering the Frame Control Field, sequence
+///number and the (variable length, depending on the addressing modes)
+///addressing fields. Auxiliary security headers & the MAC footer (FCS) are
+///not decoded.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Ieee802154Header {
+    pub frame_type: Ieee802154FrameType,
+    pub security_enabled: bool,
+    pub frame_pending: bool,
+    pub ack_request: bool,
+    pub pan_id_compression: bool,
+    pub sequence_number: u8,
+    pub dest_pan_id: Option<u16>,
+    pub dest_address: Ieee802154Address,
+    pub src_pan_id: Option<u16>,
+    pub src_address: Ieee802154Address,
+}
+
+impl Ieee802154Header {
+    ///Reads an IEEE 802.15.4 MAC header from a slice. The passed slice has
+    ///to start with the first byte of the Frame Control Field.
+    pub fn read_from_slice(slice: &[u8]) -> Result<(Ieee802154Header, &[u8]), ReadError> {
+        if slice.len() < 3 {
+            return Err(ReadError::UnexpectedEndOfSlice(3));
+        }
+
+        let fcf = u16::from_le_bytes([slice[0], slice[1]]);
+        let frame_type = Ieee802154FrameType::from_u8((fcf & 0b111) as u8);
+        let security_enabled = 0 != (fcf >> 3) & 0b1;
+        let frame_pending = 0 != (fcf >> 4) & 0b1;
+        let ack_request = 0 != (fcf >> 5) & 0b1;
+        let pan_id_compression = 0 != (fcf >> 6) & 0b1;
+        let dest_addressing_mode = Ieee802154AddressingMode::from_u8(((fcf >> 10) & 0b11) as u8);
+        let src_addressing_mode = Ieee802154AddressingMode::from_u8(((fcf >> 14) & 0b11) as u8);
+
+        let sequence_number = slice[2];
+        let mut rest = &slice[3..];
+
+        //destination PAN id & address
+        let dest_pan_id = if dest_addressing_mode == Ieee802154AddressingMode::None {
+            None
+        } else {
+            Some(read_u16_le(&mut rest)?)
+        };
+        let dest_address = Self::read_address(&mut rest, dest_addressing_mode)?;
+
+        //source PAN id is omitted if pan_id_compression is set
+        let src_pan_id = if src_addressing_mode == Ieee802154AddressingMode::None || pan_id_compression {
+            None
+        } else {
+            Some(read_u16_le(&mut rest)?)
+        };
+        let src_address = Self::read_address(&mut rest, src_addressing_mode)?;
+
+        Ok((
+            Ieee802154Header{
+                frame_type,
+                security_enabled,
+                frame_pending,
+                ack_request,
+                pan_id_compression,
+                sequence_number,
+                dest_pan_id,
+                dest_address,
+                src_pan_id,
+                src_address,
+            },
+            rest,
+        ))
+    }
+
+    fn read_address(rest: &mut &[u8], mode: Ieee802154AddressingMode) -> Result<Ieee802154Address, ReadError> {
+        let len = mode.len();
+        if rest.len() < len {
+            return Err(ReadError::UnexpectedEndOfSlice(len));
+        }
+        let (addr_bytes, new_rest) = rest.split_at(len);
+        *rest = new_rest;
+        Ok(match mode {
+            Ieee802154AddressingMode::None | Ieee802154AddressingMode::Reserved => Ieee802154Address::None,
+            Ieee802154AddressingMode::Short => Ieee802154Address::Short([addr_bytes[0], addr_bytes[1]]),
+            Ieee802154AddressingMode::Extended => {
+                let mut bytes = [0u8;8];
+                bytes.copy_from_slice(addr_bytes);
+                Ieee802154Address::Extended(bytes)
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_short_addressed_data_frame() {
+        //frame type = Data(1), dest & src addressing mode = Short(0b10), PAN id compression off
+        let fcf: u16 = 1 | (0b10 << 10) | (0b10 << 14);
+        let fcf_bytes = fcf.to_le_bytes();
+        let bytes = [
+            fcf_bytes[0], fcf_bytes[1],
+            0x42, //sequence number
+            0x34, 0x12, //dest pan id (LE) = 0x1234
+            0xaa, 0xbb, //dest address
+            0x78, 0x56, //src pan id (LE) = 0x5678
+            0xcc, 0xdd, //src address
+        ];
+
+        let (header, rest) = Ieee802154Header::read_from_slice(&bytes).unwrap();
+        assert_eq!(header.frame_type, Ieee802154FrameType::Data);
+        assert!(!header.security_enabled);
+        assert!(!header.pan_id_compression);
+        assert_eq!(header.sequence_number, 0x42);
+        assert_eq!(header.dest_pan_id, Some(0x1234));
+        assert_eq!(header.dest_address, Ieee802154Address::Short([0xaa, 0xbb]));
+        assert_eq!(header.src_pan_id, Some(0x5678));
+        assert_eq!(header.src_address, Ieee802154Address::Short([0xcc, 0xdd]));
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn no_addressing_means_no_addresses() {
+        //frame type = Acknowledgment(2), no dest/src addressing at all
+        let fcf: u16 = 2;
+        let fcf_bytes = fcf.to_le_bytes();
+        let bytes = [fcf_bytes[0], fcf_bytes[1], 0x07];
+
+        let (header, rest) = Ieee802154Header::read_from_slice(&bytes).unwrap();
+        assert_eq!(header.frame_type, Ieee802154FrameType::Acknowledgment);
+        assert_eq!(header.dest_pan_id, None);
+        assert_eq!(header.dest_address, Ieee802154Address::None);
+        assert_eq!(header.src_pan_id, None);
+        assert_eq!(header.src_address, Ieee802154Address::None);
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn read_from_slice_errors_on_short_input() {
+        assert_eq!(
+            Ieee802154Header::read_from_slice(&[0, 0]).unwrap_err(),
+            ReadError::UnexpectedEndOfSlice(3)
+        );
+    }
+}
+
+fn read_u16_le(rest: &mut &[u8]) -> Result<u16, ReadError> {
+    if rest.len() < 2 {
+        return Err(ReadError::UnexpectedEndOfSlice(2));
+    }
+    let (bytes, new_rest) = rest.split_at(2);
+    *rest = new_rest;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
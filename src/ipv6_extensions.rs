@@ -0,0 +1,234 @@
+use super::*;
+
+///Maximum number of extension headers that are followed before giving up,
+///protecting against malformed or looping next-header chains.
+const IPV6_MAX_NUM_HEADER_EXTENSIONS: usize = 16;
+
+pub const IPV6_HOP_BY_HOP: u8 = IpTrafficClass::IPv6HeaderHopByHop as u8;
+pub const IPV6_ROUTE: u8 = IpTrafficClass::IPv6RouteHeader as u8;
+pub const IPV6_FRAG: u8 = IpTrafficClass::IPv6FragmentationHeader as u8;
+pub const IPV6_DEST_OPTIONS: u8 = IpTrafficClass::IPv6DestinationOptions as u8;
+pub const IPV6_AUTH: u8 = IpTrafficClass::AuthenticationHeader as u8;
+
+///A Hop-by-Hop Options, Routing or Destination Options header. The fixed
+///"Next Header"/"Hdr Ext Len" bytes are parsed out, `options` is everything
+///else up to the length given by "Hdr Ext Len" (in 8-octet units, not
+///counting the first 8 octets).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Ipv6RawExtHeader<'a> {
+    pub next_header: u8,
+    pub hdr_ext_len: u8,
+    pub options: &'a [u8],
+}
+
+impl<'a> Ipv6RawExtHeader<'a> {
+    fn read_from_slice(slice: &'a [u8]) -> Result<(Ipv6RawExtHeader<'a>, &'a [u8]), ReadError> {
+        if slice.len() < 8 {
+            return Err(ReadError::UnexpectedEndOfSlice(8));
+        }
+        let next_header = slice[0];
+        let hdr_ext_len = slice[1];
+        let len = (usize::from(hdr_ext_len) + 1) * 8;
+        if slice.len() < len {
+            return Err(ReadError::UnexpectedEndOfSlice(len));
+        }
+        Ok((
+            Ipv6RawExtHeader{
+                next_header,
+                hdr_ext_len,
+                options: &slice[2..len],
+            },
+            &slice[len..],
+        ))
+    }
+}
+
+///The IPv6 Authentication Header (RFC 4302). Unlike the other extension
+///headers its "Payload Len" field counts 4-octet words (minus 2), not
+///8-octet units.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Ipv6AuthHeader<'a> {
+    pub next_header: u8,
+    pub payload_len: u8,
+    pub spi: u32,
+    pub sequence_number: u32,
+    pub icv: &'a [u8],
+}
+
+impl<'a> Ipv6AuthHeader<'a> {
+    fn read_from_slice(slice: &'a [u8]) -> Result<(Ipv6AuthHeader<'a>, &'a [u8]), ReadError> {
+        if slice.len() < 12 {
+            return Err(ReadError::UnexpectedEndOfSlice(12));
+        }
+        let next_header = slice[0];
+        let payload_len = slice[1];
+        let len = (usize::from(payload_len) + 2) * 4;
+        if slice.len() < len {
+            return Err(ReadError::UnexpectedEndOfSlice(len));
+        }
+        Ok((
+            Ipv6AuthHeader{
+                next_header,
+                payload_len,
+                spi: u32::from_be_bytes([slice[4], slice[5], slice[6], slice[7]]),
+                sequence_number: u32::from_be_bytes([slice[8], slice[9], slice[10], slice[11]]),
+                icv: &slice[12..len],
+            },
+            &slice[len..],
+        ))
+    }
+}
+
+///The IPv6 Fragment header (RFC 8200 section 4.5). Always exactly 8 octets.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Ipv6FragmentHeader {
+    pub next_header: u8,
+    ///Offset, in 8-octet units, of this fragment's payload within the
+    ///reassembled packet.
+    pub fragment_offset: u16,
+    ///"M" flag. `true` means more fragments follow.
+    pub more_fragments: bool,
+    pub identification: u32,
+}
+
+impl Ipv6FragmentHeader {
+    const LEN: usize = 8;
+
+    fn read_from_slice(slice: &[u8]) -> Result<(Ipv6FragmentHeader, &[u8]), ReadError> {
+        if slice.len() < Self::LEN {
+            return Err(ReadError::UnexpectedEndOfSlice(Self::LEN));
+        }
+        let next_header = slice[0];
+        //slice[1] is reserved
+        let offset_and_flags = u16::from_be_bytes([slice[2], slice[3]]);
+        Ok((
+            Ipv6FragmentHeader{
+                next_header,
+                fragment_offset: offset_and_flags >> 3,
+                more_fragments: 0 != (offset_and_flags & 0b1),
+                identification: u32::from_be_bytes([slice[4], slice[5], slice[6], slice[7]]),
+            },
+            &slice[Self::LEN..],
+        ))
+    }
+}
+
+///The chain of IPv6 extension headers found between the fixed IPv6 header
+///and the transport header (or fragment payload). Each field is `None` if
+///that particular extension header was not present.
+#[derive(Clone, Debug, Eq, PartialEq, Default)]
+pub struct Ipv6Extensions<'a> {
+    pub hop_by_hop_options: Option<Ipv6RawExtHeader<'a>>,
+    pub destination_options: Option<Ipv6RawExtHeader<'a>>,
+    pub routing: Option<Ipv6RawExtHeader<'a>>,
+    pub fragment: Option<Ipv6FragmentHeader>,
+    pub auth: Option<Ipv6AuthHeader<'a>>,
+}
+
+impl<'a> Ipv6Extensions<'a> {
+    ///Reads & collects all known extension headers, starting with
+    ///`first_header` (the IPv6 header's "Next Header" field) and `rest`
+    ///pointing at the first byte after the fixed IPv6 header. Returns the
+    ///collected extensions, the next header value to hand to the transport
+    ///layer (or fragment reassembly) and the remaining slice.
+    pub fn read_from_slice(
+        first_header: u8,
+        rest: &'a [u8],
+    ) -> Result<(Ipv6Extensions<'a>, u8, &'a [u8]), ReadError> {
+        let mut result = Ipv6Extensions::default();
+        let mut next_header = first_header;
+        let mut rest = rest;
+
+        for _ in 0..IPV6_MAX_NUM_HEADER_EXTENSIONS {
+            match next_header {
+                IPV6_HOP_BY_HOP if result.hop_by_hop_options.is_none() => {
+                    let (header, new_rest) = Ipv6RawExtHeader::read_from_slice(rest)?;
+                    next_header = header.next_header;
+                    rest = new_rest;
+                    result.hop_by_hop_options = Some(header);
+                },
+                IPV6_DEST_OPTIONS if result.destination_options.is_none() => {
+                    let (header, new_rest) = Ipv6RawExtHeader::read_from_slice(rest)?;
+                    next_header = header.next_header;
+                    rest = new_rest;
+                    result.destination_options = Some(header);
+                },
+                IPV6_ROUTE if result.routing.is_none() => {
+                    let (header, new_rest) = Ipv6RawExtHeader::read_from_slice(rest)?;
+                    next_header = header.next_header;
+                    rest = new_rest;
+                    result.routing = Some(header);
+                },
+                IPV6_FRAG if result.fragment.is_none() => {
+                    let (header, new_rest) = Ipv6FragmentHeader::read_from_slice(rest)?;
+                    next_header = header.next_header;
+                    rest = new_rest;
+                    result.fragment = Some(header);
+                },
+                IPV6_AUTH if result.auth.is_none() => {
+                    let (header, new_rest) = Ipv6AuthHeader::read_from_slice(rest)?;
+                    next_header = header.next_header;
+                    rest = new_rest;
+                    result.auth = Some(header);
+                },
+                //not an extension header (or already seen once) -> stop
+                _ => return Ok((result, next_header, rest)),
+            }
+        }
+
+        Err(ReadError::Ipv6TooManyHeaderExtensions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_extensions_passes_next_header_through() {
+        let (extensions, next_header, rest) = Ipv6Extensions::read_from_slice(
+            IpTrafficClass::Tcp as u8,
+            &[1, 2, 3],
+        ).unwrap();
+        assert_eq!(extensions, Ipv6Extensions::default());
+        assert_eq!(next_header, IpTrafficClass::Tcp as u8);
+        assert_eq!(rest, &[1, 2, 3]);
+    }
+
+    #[test]
+    fn reads_hop_by_hop_then_fragment_then_transport() {
+        //hop-by-hop: next header = fragment, hdr ext len = 0 (=> 8 byte header)
+        let hop_by_hop = [IPV6_FRAG, 0, 0, 0, 0, 0, 0, 0];
+        //fragment header: next header = UDP, offset = 5 (*8 = 40), M = 1
+        let offset_and_flags = (5u16 << 3 | 1).to_be_bytes();
+        let fragment = [IpTrafficClass::Udp as u8, 0, offset_and_flags[0], offset_and_flags[1], 0, 0, 0, 9];
+        let payload = [0xaa, 0xbb];
+
+        let mut slice = Vec::new();
+        slice.extend_from_slice(&hop_by_hop);
+        slice.extend_from_slice(&fragment);
+        slice.extend_from_slice(&payload);
+
+        let (extensions, next_header, rest) = Ipv6Extensions::read_from_slice(IPV6_HOP_BY_HOP, &slice).unwrap();
+        assert!(extensions.hop_by_hop_options.is_some());
+        let frag = extensions.fragment.unwrap();
+        assert_eq!(frag.fragment_offset, 5);
+        assert!(frag.more_fragments);
+        assert_eq!(frag.identification, 9);
+        assert_eq!(next_header, IpTrafficClass::Udp as u8);
+        assert_eq!(rest, &payload);
+    }
+
+    #[test]
+    fn stops_on_a_repeated_extension_header() {
+        //a second hop-by-hop header should not be consumed as an extension header
+        let hop_by_hop = [IPV6_HOP_BY_HOP, 0, 0, 0, 0, 0, 0, 0];
+        let mut slice = Vec::new();
+        slice.extend_from_slice(&hop_by_hop);
+        slice.extend_from_slice(&hop_by_hop);
+        let (extensions, next_header, rest) = Ipv6Extensions::read_from_slice(IPV6_HOP_BY_HOP, &slice).unwrap();
+        assert!(extensions.hop_by_hop_options.is_some());
+        assert_eq!(next_header, IPV6_HOP_BY_HOP);
+        assert_eq!(rest, &hop_by_hop[..]);
+    }
+}
@@ -0,0 +1,8 @@
+use super::*;
+
+///The link layer headers `PacketHeaders` currently knows how to decode.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LinkHeader {
+    Ethernet2(Ethernet2Header),
+    Ieee802154(Ieee802154Header),
+}
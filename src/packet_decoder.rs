@@ -5,10 +5,27 @@ use super::*;
 /// to decode and get this struct as a result.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct PacketHeaders<'a> {
-    pub link: Option<Ethernet2Header>,
+    pub link: Option<LinkHeader>,
     pub vlan: Option<VlanHeader>,
     pub ip: Option<IpHeader>,
+    ///IPv6 extension headers present between the IPv6 header and the transport
+    ///header. `None` for non-IPv6 packets; always `Some(..)` for IPv6 packets,
+    ///with every field `None` if no extension headers were present.
+    pub ipv6_extensions: Option<Ipv6Extensions<'a>>,
     pub transport: Option<TransportHeader>,
+    ///Present if the ethernet frame's EtherType was ARP (0x0806) instead of
+    ///an IPv4/IPv6 EtherType.
+    pub arp: Option<ArpHeader<'a>>,
+    ///Present if `link` is a fragment of a 6LoWPAN datagram (FRAG1/FRAGN).
+    ///`from_ieee802154_slice` does not reassemble fragments itself -- while
+    ///this is `Some(..)`, `ip`/`transport` are always `None` and `payload` is
+    ///just this fragment's raw (still possibly IPHC-compressed) share of the
+    ///datagram. `FragmentBuffer` reassembles these bytes as they are, placing
+    ///each fragment at its FRAGN offset (counted in octets of the uncompressed
+    ///datagram) and completing once `datagram_size` octets are covered. It does
+    ///not decompress IPHC headers, so the bytes of a datagram whose FRAG1
+    ///payload is IPHC-compressed do not line up with the FRAGN offsets.
+    pub sixlowpan_fragment: Option<SixLowPanFragHeader>,
     ///Rest of the packet that could not be decoded as a header (usually the payload).
     pub payload: &'a [u8]
 }
@@ -16,15 +33,18 @@ pub struct PacketHeaders<'a> {
 impl<'a> PacketHeaders<'a> {
     ///Tries to decode as much as possible of a packet.
     pub fn from_ethernet_slice(packet: &[u8]) -> Result<PacketHeaders, ReadError> {
-        
+
         let (ethernet, mut rest) = Ethernet2Header::read_from_slice(&packet)?;
         let mut ether_type = ethernet.ether_type;
 
         let mut result = PacketHeaders{
-            link: Some(ethernet),
+            link: Some(LinkHeader::Ethernet2(ethernet)),
             vlan: None,
             ip: None,
+            ipv6_extensions: None,
             transport: None,
+            arp: None,
+            sixlowpan_fragment: None,
             payload: &[]
         };
 
@@ -71,8 +91,14 @@ impl<'a> PacketHeaders<'a> {
         //parse ip (if present)
         const IPV4: u16 = Ipv4 as u16;
         const IPV6: u16 = Ipv6 as u16;
+        const ARP: u16 = Arp as u16;
 
         match ether_type {
+            ARP => {
+                let (arp, arp_rest) = ArpHeader::read_from_slice(rest)?;
+                rest = arp_rest;
+                result.arp = Some(arp);
+            },
             IPV4 => {
                 let (ip, ip_rest) = Ipv4Header::read_from_slice(rest)?;
 
@@ -81,15 +107,21 @@ impl<'a> PacketHeaders<'a> {
 
                 //set the ip result & rest
                 rest = ip_rest;
+                let is_fragment = ip.more_fragments || 0 != ip.fragments_offset;
                 result.ip = Some(IpHeader::Version4(ip));
 
-                //parse the transport layer
-                let (transport, transport_rest) = read_transport(ip_protocol, rest)?;
-
-                //assign to the output
-                rest = transport_rest;
-                result.transport = transport;
-                
+                //a fragment only carries a transport header in its first (offset == 0)
+                //piece, and even then the reassembly engine needs those bytes intact --
+                //leave rest/payload untouched for fragments instead of parsing a transport
+                //header out of it.
+                if !is_fragment {
+                    //parse the transport layer
+                    let (transport, transport_rest) = read_transport(ip_protocol, rest)?;
+
+                    //assign to the output
+                    rest = transport_rest;
+                    result.transport = transport;
+                }
             },
             IPV6 => {
                 let (ip, ip_rest) = Ipv6Header::read_from_slice(rest)?;
@@ -101,18 +133,23 @@ impl<'a> PacketHeaders<'a> {
                 rest = ip_rest;
                 result.ip = Some(IpHeader::Version6(ip));
 
-                //skip the header extensions
-                let (next_header, ip_rest) = Ipv6Header::skip_all_header_extensions_in_slice(rest, next_header)?;
-                
+                //parse & collect the header extensions
+                let (extensions, next_header, ip_rest) = Ipv6Extensions::read_from_slice(next_header, rest)?;
+
                 //set the rest
                 rest = ip_rest;
+                let is_fragment = extensions.fragment.is_some();
+                result.ipv6_extensions = Some(extensions);
 
-                //parse the transport layer
-                let (transport, transport_rest) = read_transport(next_header, rest)?;
-                
-                rest = transport_rest;
-                result.transport = transport;
+                //see the comment on the IPv4 branch above: fragments keep their
+                //raw post-header bytes so the reassembly engine gets an intact payload.
+                if !is_fragment {
+                    //parse the transport layer
+                    let (transport, transport_rest) = read_transport(next_header, rest)?;
 
+                    rest = transport_rest;
+                    result.transport = transport;
+                }
             },
             _ => {}
         }
@@ -158,34 +195,108 @@ impl<'a> PacketHeaders<'a> {
             link: None,
             vlan: None,
             ip: None,
+            ipv6_extensions: None,
             transport: None,
+            arp: None,
+            sixlowpan_fragment: None,
             payload: &[],
         };
 
-        let (transport_proto, rest) = {
+        let (transport_proto, is_fragment, rest) = {
             use crate::IpHeader;
             let (ip, rest) = IpHeader::read_from_slice(packet)?;
 
             // grab transport protocol
-            let (transport_proto, rest) = match &ip {
-                IpHeader::Version4(h) => (h.protocol, rest),
+            let (transport_proto, is_fragment, rest) = match &ip {
+                IpHeader::Version4(h) => (h.protocol, h.more_fragments || 0 != h.fragments_offset, rest),
                 IpHeader::Version6(h) => {
-                    Ipv6Header::skip_all_header_extensions_in_slice(rest, h.next_header)?
+                    let (extensions, next_header, rest) = Ipv6Extensions::read_from_slice(h.next_header, rest)?;
+                    let is_fragment = extensions.fragment.is_some();
+                    result.ipv6_extensions = Some(extensions);
+                    (next_header, is_fragment, rest)
                 },
             };
 
             // update output
             result.ip = Some(ip);
-            (transport_proto, rest)
+            (transport_proto, is_fragment, rest)
         };
 
-        // try to parse the transport header
-        let (transport, rest) = read_transport(transport_proto, rest)?;
+        // a fragment's payload has to stay intact for the reassembly engine, so
+        // don't try to carve a transport header out of it (see from_ethernet_slice).
+        if !is_fragment {
+            // try to parse the transport header
+            let (transport, rest) = read_transport(transport_proto, rest)?;
 
-        // update output
-        result.transport = transport;
+            // update output
+            result.transport = transport;
+            result.payload = rest;
+        } else {
+            result.payload = rest;
+        }
 
-        result.payload = rest;
+        Ok(result)
+    }
+
+    ///Tries to decode as much as possible of an IEEE 802.15.4 / 6LoWPAN packet.
+    ///Mirrors `from_ethernet_slice`, but starts at the IEEE 802.15.4 MAC
+    ///header instead of an `Ethernet2Header`.
+    pub fn from_ieee802154_slice(packet: &[u8]) -> Result<PacketHeaders, ReadError> {
+        let (mac, rest) = Ieee802154Header::read_from_slice(packet)?;
+        let link_src = mac.src_address;
+        let link_dest = mac.dest_address;
+
+        let mut result = PacketHeaders{
+            link: Some(LinkHeader::Ieee802154(mac)),
+            vlan: None,
+            ip: None,
+            ipv6_extensions: None,
+            transport: None,
+            arp: None,
+            sixlowpan_fragment: None,
+            payload: &[],
+        };
+
+        //6LoWPAN fragmentation headers (FRAG1/FRAGN) are only decoded, not
+        //reassembled here -- the caller is expected to feed the result into
+        //the fragment reassembly engine.
+        let rest = match SixLowPanFragHeader::read_from_slice(rest)? {
+            Some((frag, frag_rest)) => {
+                result.sixlowpan_fragment = Some(frag);
+                frag_rest
+            },
+            None => rest,
+        };
+
+        //a fragment (FRAG1 or FRAGN) only carries a raw slice of the (possibly
+        //still IPHC-compressed) datagram -- it has to go through
+        //`FragmentBuffer` before it can be decoded, so leave ip/transport
+        //unset and hand back the raw bytes as payload.
+        if result.sixlowpan_fragment.is_some() {
+            result.payload = rest;
+            return Ok(result);
+        }
+
+        match sixlowpan::decompress_iphc(rest, link_src, link_dest) {
+            Ok((mut ip, next_header, ip_rest)) => {
+                //everything after the (virtual) fixed IPv6 header counts towards
+                //the payload length, extension headers included
+                ip.payload_length = ip_rest.len() as u16;
+
+                let (extensions, next_header, ip_rest) = Ipv6Extensions::read_from_slice(next_header, ip_rest)?;
+                result.ipv6_extensions = Some(extensions);
+
+                let (transport, transport_rest) = read_transport(next_header, ip_rest)?;
+                result.transport = transport;
+                result.ip = Some(IpHeader::Version6(ip));
+                result.payload = transport_rest;
+            },
+            //not every 6LoWPAN payload is a compressed IPv6 header (e.g. mesh
+            //addressing or uncompressed IPv6); fall back to raw payload.
+            Err(_) => {
+                result.payload = rest;
+            },
+        }
 
         Ok(result)
     }
@@ -199,11 +310,17 @@ fn read_transport(
     use crate::IpTrafficClass::*;
     const UDP: u8 = Udp as u8;
     const TCP: u8 = Tcp as u8;
+    const ICMP: u8 = Icmp as u8;
+    const ICMP6: u8 = Ipv6Icmp as u8;
     match protocol {
         UDP => Ok(UdpHeader::read_from_slice(rest)
             .map(|value| (Some(TransportHeader::Udp(value.0)), value.1))?),
         TCP => Ok(TcpHeader::read_from_slice(rest)
             .map(|value| (Some(TransportHeader::Tcp(value.0)), value.1))?),
+        ICMP => Ok(Icmpv4Header::read_from_slice(rest)
+            .map(|value| (Some(TransportHeader::Icmpv4(value.0)), value.1))?),
+        ICMP6 => Ok(Icmpv6Header::read_from_slice(rest)
+            .map(|value| (Some(TransportHeader::Icmpv6(value.0)), value.1))?),
         _ => Ok((None, rest)),
     }
 }
\ No newline at end of file
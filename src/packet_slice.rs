@@ -0,0 +1,576 @@
+use super::*;
+
+///Distinguishes a value that could be fully parsed from one that was cut
+///short by a truncated capture - the remaining bytes are kept so the caller
+///can still see what is there instead of the whole packet erroring out.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MaybeParsed<'a, T> {
+    Parsed(T),
+    Incomplete(&'a [u8]),
+}
+
+///View over an `Ethernet2Header` that reads fields directly out of the
+///underlying slice instead of copying them into an owned struct.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Ethernet2Slice<'a>(&'a [u8]);
+
+impl<'a> Ethernet2Slice<'a> {
+    pub const LEN: usize = 14;
+
+    pub fn destination(&self) -> [u8;6] {
+        let mut result = [0u8;6];
+        result.copy_from_slice(&self.0[0..6]);
+        result
+    }
+    pub fn source(&self) -> [u8;6] {
+        let mut result = [0u8;6];
+        result.copy_from_slice(&self.0[6..12]);
+        result
+    }
+    pub fn ether_type(&self) -> u16 {
+        u16::from_be_bytes([self.0[12], self.0[13]])
+    }
+    pub fn slice(&self) -> &'a [u8] {
+        self.0
+    }
+}
+
+///View over a `SingleVlanHeader`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SingleVlanSlice<'a>(&'a [u8]);
+
+impl<'a> SingleVlanSlice<'a> {
+    pub const LEN: usize = 4;
+
+    pub fn priority_code_point(&self) -> u8 {
+        self.0[0] >> 5
+    }
+    pub fn drop_eligible_indicator(&self) -> bool {
+        0 != (self.0[0] >> 4) & 0b1
+    }
+    pub fn vlan_identifier(&self) -> u16 {
+        u16::from_be_bytes([self.0[0] & 0b0000_1111, self.0[1]])
+    }
+    pub fn ether_type(&self) -> u16 {
+        u16::from_be_bytes([self.0[2], self.0[3]])
+    }
+    pub fn slice(&self) -> &'a [u8] {
+        self.0
+    }
+}
+
+///View over whichever VLAN tag(s) are present ahead of the payload EtherType.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum VlanSlice<'a> {
+    Single(SingleVlanSlice<'a>),
+    Double{ outer: SingleVlanSlice<'a>, inner: SingleVlanSlice<'a> },
+}
+
+///View over an `Ipv4Header`. Covers the full (options included) header, as
+///given by the Internet Header Length (IHL) field.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Ipv4HeaderSlice<'a>(&'a [u8]);
+
+impl<'a> Ipv4HeaderSlice<'a> {
+    pub fn ihl(&self) -> u8 {
+        self.0[0] & 0b1111
+    }
+    pub fn total_len(&self) -> u16 {
+        u16::from_be_bytes([self.0[2], self.0[3]])
+    }
+    pub fn identification(&self) -> u16 {
+        u16::from_be_bytes([self.0[4], self.0[5]])
+    }
+    pub fn dont_fragment(&self) -> bool {
+        0 != (self.0[6] >> 6) & 0b1
+    }
+    pub fn more_fragments(&self) -> bool {
+        0 != (self.0[6] >> 5) & 0b1
+    }
+    pub fn fragments_offset(&self) -> u16 {
+        u16::from_be_bytes([self.0[6] & 0b0001_1111, self.0[7]])
+    }
+    pub fn ttl(&self) -> u8 {
+        self.0[8]
+    }
+    pub fn protocol(&self) -> u8 {
+        self.0[9]
+    }
+    pub fn header_checksum(&self) -> u16 {
+        u16::from_be_bytes([self.0[10], self.0[11]])
+    }
+    pub fn source(&self) -> [u8;4] {
+        [self.0[12], self.0[13], self.0[14], self.0[15]]
+    }
+    pub fn destination(&self) -> [u8;4] {
+        [self.0[16], self.0[17], self.0[18], self.0[19]]
+    }
+    ///Options following the fixed 20 byte header, if the IHL indicates any.
+    pub fn options(&self) -> &'a [u8] {
+        &self.0[20..]
+    }
+    pub fn slice(&self) -> &'a [u8] {
+        self.0
+    }
+}
+
+///View over the fixed (40 byte) part of an `Ipv6Header`. Extension headers
+///that follow it are walked (using the same logic as `Ipv6Extensions`) to
+///find the transport protocol, but are not themselves exposed as typed
+///views in this fast path.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Ipv6HeaderSlice<'a>(&'a [u8]);
+
+impl<'a> Ipv6HeaderSlice<'a> {
+    pub const LEN: usize = 40;
+
+    pub fn traffic_class(&self) -> u8 {
+        (self.0[0] << 4) | (self.0[1] >> 4)
+    }
+    pub fn flow_label(&self) -> u32 {
+        u32::from_be_bytes([0, self.0[1] & 0b1111, self.0[2], self.0[3]])
+    }
+    pub fn payload_length(&self) -> u16 {
+        u16::from_be_bytes([self.0[4], self.0[5]])
+    }
+    pub fn next_header(&self) -> u8 {
+        self.0[6]
+    }
+    pub fn hop_limit(&self) -> u8 {
+        self.0[7]
+    }
+    pub fn source(&self) -> [u8;16] {
+        let mut result = [0u8;16];
+        result.copy_from_slice(&self.0[8..24]);
+        result
+    }
+    pub fn destination(&self) -> [u8;16] {
+        let mut result = [0u8;16];
+        result.copy_from_slice(&self.0[24..40]);
+        result
+    }
+    pub fn slice(&self) -> &'a [u8] {
+        self.0
+    }
+}
+
+///View over whichever IP header is present.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IpSlice<'a> {
+    Ipv4(Ipv4HeaderSlice<'a>),
+    Ipv6(Ipv6HeaderSlice<'a>),
+}
+
+///View over a `UdpHeader`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct UdpHeaderSlice<'a>(&'a [u8]);
+
+impl<'a> UdpHeaderSlice<'a> {
+    pub const LEN: usize = 8;
+
+    pub fn source_port(&self) -> u16 {
+        u16::from_be_bytes([self.0[0], self.0[1]])
+    }
+    pub fn destination_port(&self) -> u16 {
+        u16::from_be_bytes([self.0[2], self.0[3]])
+    }
+    pub fn length(&self) -> u16 {
+        u16::from_be_bytes([self.0[4], self.0[5]])
+    }
+    pub fn checksum(&self) -> u16 {
+        u16::from_be_bytes([self.0[6], self.0[7]])
+    }
+    pub fn slice(&self) -> &'a [u8] {
+        self.0
+    }
+}
+
+///View over a `TcpHeader`. Covers the full (options included) header, as
+///given by the Data Offset field.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TcpHeaderSlice<'a>(&'a [u8]);
+
+impl<'a> TcpHeaderSlice<'a> {
+    pub fn source_port(&self) -> u16 {
+        u16::from_be_bytes([self.0[0], self.0[1]])
+    }
+    pub fn destination_port(&self) -> u16 {
+        u16::from_be_bytes([self.0[2], self.0[3]])
+    }
+    pub fn sequence_number(&self) -> u32 {
+        u32::from_be_bytes([self.0[4], self.0[5], self.0[6], self.0[7]])
+    }
+    pub fn acknowledgment_number(&self) -> u32 {
+        u32::from_be_bytes([self.0[8], self.0[9], self.0[10], self.0[11]])
+    }
+    pub fn data_offset(&self) -> u8 {
+        self.0[12] >> 4
+    }
+    pub fn window_size(&self) -> u16 {
+        u16::from_be_bytes([self.0[14], self.0[15]])
+    }
+    pub fn checksum(&self) -> u16 {
+        u16::from_be_bytes([self.0[16], self.0[17]])
+    }
+    pub fn urgent_pointer(&self) -> u16 {
+        u16::from_be_bytes([self.0[18], self.0[19]])
+    }
+    ///Options following the fixed 20 byte header, as indicated by `data_offset`.
+    pub fn options(&self) -> &'a [u8] {
+        &self.0[20..(usize::from(self.data_offset()) * 4)]
+    }
+    pub fn slice(&self) -> &'a [u8] {
+        self.0
+    }
+}
+
+///View over whichever transport header is present.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TransportSlice<'a> {
+    Udp(UdpHeaderSlice<'a>),
+    Tcp(TcpHeaderSlice<'a>),
+}
+
+///A borrowing, lazily parsed counterpart to `PacketHeaders`. Only the
+///fixed-size boundaries of each header are validated up front; the actual
+///field values are decoded on demand by the accessor methods, directly out
+///of the backing slice. Intended for high-throughput filtering/forwarding
+///paths where most packets only need a handful of fields inspected.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PacketSlice<'a> {
+    ethernet: Option<Ethernet2Slice<'a>>,
+    vlan: Option<VlanSlice<'a>>,
+    ip: Option<IpSlice<'a>>,
+    transport: Option<MaybeParsed<'a, TransportSlice<'a>>>,
+    payload: &'a [u8],
+}
+
+impl<'a> PacketSlice<'a> {
+    ///Walks the ethernet/vlan/ip/transport boundaries of `packet`, without
+    ///decoding any of the header fields.
+    pub fn from_ethernet(packet: &'a [u8]) -> Result<PacketSlice<'a>, ReadError> {
+        if packet.len() < Ethernet2Slice::LEN {
+            return Err(ReadError::UnexpectedEndOfSlice(Ethernet2Slice::LEN));
+        }
+        let ethernet = Ethernet2Slice(&packet[..Ethernet2Slice::LEN]);
+        let mut rest = &packet[Ethernet2Slice::LEN..];
+        let mut ether_type = ethernet.ether_type();
+
+        let vlan = Self::read_vlan(&mut rest, &mut ether_type)?;
+        let (ip, transport, payload) = Self::read_ip_and_transport(ether_type, rest)?;
+
+        Ok(PacketSlice{
+            ethernet: Some(ethernet),
+            vlan,
+            ip,
+            transport,
+            payload,
+        })
+    }
+
+    ///Like `from_ethernet`, but starts directly at the IP header (no link layer).
+    pub fn from_ip(packet: &'a [u8]) -> Result<PacketSlice<'a>, ReadError> {
+        if packet.is_empty() {
+            return Err(ReadError::UnexpectedEndOfSlice(1));
+        }
+        let ip_version = packet[0] >> 4;
+        let ether_type = match ip_version {
+            4 => EtherType::Ipv4 as u16,
+            6 => EtherType::Ipv6 as u16,
+            version => return Err(ReadError::IpUnsupportedVersion(version)),
+        };
+        let (ip, transport, payload) = Self::read_ip_and_transport(ether_type, packet)?;
+
+        Ok(PacketSlice{
+            ethernet: None,
+            vlan: None,
+            ip,
+            transport,
+            payload,
+        })
+    }
+
+    fn read_vlan(rest: &mut &'a [u8], ether_type: &mut u16) -> Result<Option<VlanSlice<'a>>, ReadError> {
+        use crate::EtherType::*;
+        const VLAN_TAGGED_FRAME: u16 = VlanTaggedFrame as u16;
+        const PROVIDER_BRIDGING: u16 = ProviderBridging as u16;
+        const VLAN_DOUBLE_TAGGED_FRAME: u16 = VlanDoubleTaggedFrame as u16;
+
+        match *ether_type {
+            VLAN_TAGGED_FRAME | PROVIDER_BRIDGING | VLAN_DOUBLE_TAGGED_FRAME => {
+                let outer = Self::read_single_vlan(rest)?;
+                *ether_type = outer.ether_type();
+
+                match *ether_type {
+                    VLAN_TAGGED_FRAME | PROVIDER_BRIDGING | VLAN_DOUBLE_TAGGED_FRAME => {
+                        let inner = Self::read_single_vlan(rest)?;
+                        *ether_type = inner.ether_type();
+                        Ok(Some(VlanSlice::Double{ outer, inner }))
+                    },
+                    _ => Ok(Some(VlanSlice::Single(outer))),
+                }
+            },
+            _ => Ok(None),
+        }
+    }
+
+    fn read_single_vlan(rest: &mut &'a [u8]) -> Result<SingleVlanSlice<'a>, ReadError> {
+        if rest.len() < SingleVlanSlice::LEN {
+            return Err(ReadError::UnexpectedEndOfSlice(SingleVlanSlice::LEN));
+        }
+        let (header, new_rest) = rest.split_at(SingleVlanSlice::LEN);
+        *rest = new_rest;
+        Ok(SingleVlanSlice(header))
+    }
+
+    fn read_ip_and_transport(
+        ether_type: u16,
+        rest: &'a [u8],
+    ) -> Result<(Option<IpSlice<'a>>, Option<MaybeParsed<'a, TransportSlice<'a>>>, &'a [u8]), ReadError> {
+        use crate::EtherType::*;
+        const IPV4: u16 = Ipv4 as u16;
+        const IPV6: u16 = Ipv6 as u16;
+
+        match ether_type {
+            IPV4 => {
+                if rest.len() < 20 {
+                    return Err(ReadError::UnexpectedEndOfSlice(20));
+                }
+                let ihl_len = usize::from(rest[0] & 0b1111) * 4;
+                if ihl_len < 20 || rest.len() < ihl_len {
+                    return Err(ReadError::UnexpectedEndOfSlice(ihl_len.max(20)));
+                }
+                let header = Ipv4HeaderSlice(&rest[..ihl_len]);
+                let payload = &rest[ihl_len..];
+                //non-initial fragments don't carry a transport header -- the bytes
+                //that follow are just this fragment's share of the original payload
+                let is_fragment = header.more_fragments() || 0 != header.fragments_offset();
+                let (transport, payload) = if is_fragment {
+                    (None, payload)
+                } else {
+                    Self::read_transport(header.protocol(), payload)
+                };
+                Ok((Some(IpSlice::Ipv4(header)), transport, payload))
+            },
+            IPV6 => {
+                if rest.len() < Ipv6HeaderSlice::LEN {
+                    return Err(ReadError::UnexpectedEndOfSlice(Ipv6HeaderSlice::LEN));
+                }
+                let header = Ipv6HeaderSlice(&rest[..Ipv6HeaderSlice::LEN]);
+
+                //walk (but don't otherwise expose) the extension header chain,
+                //same as `PacketHeaders`, so `next_header` actually names the
+                //transport protocol instead of e.g. a Fragment header
+                let (extensions, next_header, rest) = Ipv6Extensions::read_from_slice(
+                    header.next_header(),
+                    &rest[Ipv6HeaderSlice::LEN..],
+                )?;
+                let (transport, payload) = if extensions.fragment.is_some() {
+                    (None, rest)
+                } else {
+                    Self::read_transport(next_header, rest)
+                };
+                Ok((Some(IpSlice::Ipv6(header)), transport, payload))
+            },
+            _ => Ok((None, None, rest)),
+        }
+    }
+
+    fn read_transport(protocol: u8, rest: &'a [u8]) -> (Option<MaybeParsed<'a, TransportSlice<'a>>>, &'a [u8]) {
+        use crate::IpTrafficClass::*;
+        const UDP: u8 = Udp as u8;
+        const TCP: u8 = Tcp as u8;
+        match protocol {
+            UDP => {
+                if rest.len() < UdpHeaderSlice::LEN {
+                    (Some(MaybeParsed::Incomplete(rest)), &rest[rest.len()..])
+                } else {
+                    let (header, payload) = rest.split_at(UdpHeaderSlice::LEN);
+                    (Some(MaybeParsed::Parsed(TransportSlice::Udp(UdpHeaderSlice(header)))), payload)
+                }
+            },
+            TCP => {
+                if rest.len() < 20 {
+                    (Some(MaybeParsed::Incomplete(rest)), &rest[rest.len()..])
+                } else {
+                    let data_offset_len = usize::from(rest[12] >> 4) * 4;
+                    if data_offset_len < 20 || rest.len() < data_offset_len {
+                        (Some(MaybeParsed::Incomplete(rest)), &rest[rest.len()..])
+                    } else {
+                        let (header, payload) = rest.split_at(data_offset_len);
+                        (Some(MaybeParsed::Parsed(TransportSlice::Tcp(TcpHeaderSlice(header)))), payload)
+                    }
+                }
+            },
+            _ => (None, rest),
+        }
+    }
+
+    pub fn ethernet2(&self) -> Option<Ethernet2Slice<'a>> {
+        self.ethernet
+    }
+    pub fn vlan(&self) -> Option<VlanSlice<'a>> {
+        self.vlan
+    }
+    pub fn ip(&self) -> Option<IpSlice<'a>> {
+        self.ip
+    }
+    pub fn ipv4(&self) -> Option<Ipv4HeaderSlice<'a>> {
+        match self.ip {
+            Some(IpSlice::Ipv4(header)) => Some(header),
+            _ => None,
+        }
+    }
+    pub fn ipv6(&self) -> Option<Ipv6HeaderSlice<'a>> {
+        match self.ip {
+            Some(IpSlice::Ipv6(header)) => Some(header),
+            _ => None,
+        }
+    }
+    pub fn transport(&self) -> Option<MaybeParsed<'a, TransportSlice<'a>>> {
+        self.transport
+    }
+    pub fn udp(&self) -> Option<UdpHeaderSlice<'a>> {
+        match self.transport {
+            Some(MaybeParsed::Parsed(TransportSlice::Udp(header))) => Some(header),
+            _ => None,
+        }
+    }
+    pub fn tcp(&self) -> Option<TcpHeaderSlice<'a>> {
+        match self.transport {
+            Some(MaybeParsed::Parsed(TransportSlice::Tcp(header))) => Some(header),
+            _ => None,
+        }
+    }
+    pub fn payload(&self) -> &'a [u8] {
+        self.payload
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn udp_bytes(source_port: u16, destination_port: u16, payload: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&source_port.to_be_bytes());
+        bytes.extend_from_slice(&destination_port.to_be_bytes());
+        bytes.extend_from_slice(&((8 + payload.len()) as u16).to_be_bytes());
+        bytes.extend_from_slice(&[0, 0]); //checksum
+        bytes.extend_from_slice(payload);
+        bytes
+    }
+
+    #[test]
+    fn from_ip_reads_ipv4_udp() {
+        let payload = [1, 2, 3];
+        let udp = udp_bytes(1234, 80, &payload);
+
+        let mut bytes = vec![
+            0x45, 0, 0, 0, //version/ihl, dscp/ecn, total len (unused by the slice view)
+            0, 0, 0, 0, //identification, flags/fragment offset
+            64, IpTrafficClass::Udp as u8, 0, 0, //ttl, protocol, header checksum
+            192, 168, 0, 1, //source
+            192, 168, 0, 2, //destination
+        ];
+        bytes.extend_from_slice(&udp);
+
+        let slice = PacketSlice::from_ip(&bytes).unwrap();
+        let ip = slice.ipv4().unwrap();
+        assert_eq!(ip.protocol(), IpTrafficClass::Udp as u8);
+        assert_eq!(ip.source(), [192, 168, 0, 1]);
+        assert_eq!(ip.destination(), [192, 168, 0, 2]);
+
+        let transport = slice.udp().unwrap();
+        assert_eq!(transport.source_port(), 1234);
+        assert_eq!(transport.destination_port(), 80);
+        assert_eq!(slice.payload(), &payload);
+    }
+
+    #[test]
+    fn from_ip_skips_ipv6_hop_by_hop_extension_before_finding_udp() {
+        //regression test for the IPv6 extension header chain not being walked
+        //before dispatching to the transport layer
+        let payload = [9, 9];
+        let udp = udp_bytes(1, 2, &payload);
+
+        //hop-by-hop extension header: next header = UDP, hdr ext len = 0 (=> 8 byte header)
+        let hop_by_hop = [IpTrafficClass::Udp as u8, 0, 0, 0, 0, 0, 0, 0];
+
+        let mut bytes = vec![
+            0x60, 0, 0, 0, //version/traffic class/flow label
+            0, 0, //payload length (unused by the slice view)
+            IPV6_HOP_BY_HOP, 64, //next header, hop limit
+        ];
+        bytes.extend_from_slice(&[0xfe, 0x80, 0,0,0,0,0,0,0,0,0,0,0,0,0,1]); //source
+        bytes.extend_from_slice(&[0xfe, 0x80, 0,0,0,0,0,0,0,0,0,0,0,0,0,2]); //destination
+        bytes.extend_from_slice(&hop_by_hop);
+        bytes.extend_from_slice(&udp);
+
+        let slice = PacketSlice::from_ip(&bytes).unwrap();
+        let ip = slice.ipv6().unwrap();
+        assert_eq!(ip.next_header(), IPV6_HOP_BY_HOP);
+
+        let transport = slice.udp().unwrap();
+        assert_eq!(transport.source_port(), 1);
+        assert_eq!(transport.destination_port(), 2);
+        assert_eq!(slice.payload(), &payload);
+    }
+
+    #[test]
+    fn from_ip_does_not_parse_transport_out_of_a_non_initial_ipv4_fragment() {
+        //regression test: a non-initial fragment's bytes must not be
+        //reinterpreted as a UDP/TCP header
+        let fragment_payload = [0xaa, 0xbb, 0xcc, 0xdd];
+        let fragment_offset: u16 = 5; //non-zero -> not the first fragment
+
+        let mut bytes = vec![
+            0x45, 0, 0, 0, //version/ihl, dscp/ecn, total len (unused by the slice view)
+            0, 0, //identification
+            (fragment_offset >> 8) as u8, (fragment_offset & 0xff) as u8, //flags/fragment offset
+            64, IpTrafficClass::Udp as u8, 0, 0, //ttl, protocol, header checksum
+            192, 168, 0, 1, //source
+            192, 168, 0, 2, //destination
+        ];
+        bytes.extend_from_slice(&fragment_payload);
+
+        let slice = PacketSlice::from_ip(&bytes).unwrap();
+        let ip = slice.ipv4().unwrap();
+        assert_eq!(ip.fragments_offset(), fragment_offset);
+        assert!(slice.transport().is_none());
+        assert_eq!(slice.payload(), &fragment_payload);
+    }
+
+    #[test]
+    fn from_ip_does_not_parse_transport_out_of_an_ipv6_fragment() {
+        let fragment_payload = [0xaa, 0xbb];
+
+        //fragment header: next header = UDP, offset = 5 (*8 = 40), M = 1
+        let offset_and_flags = (5u16 << 3 | 1).to_be_bytes();
+        let fragment = [IpTrafficClass::Udp as u8, 0, offset_and_flags[0], offset_and_flags[1], 0, 0, 0, 9];
+
+        let mut bytes = vec![
+            0x60, 0, 0, 0, //version/traffic class/flow label
+            0, 0, //payload length (unused by the slice view)
+            IPV6_FRAG, 64, //next header, hop limit
+        ];
+        bytes.extend_from_slice(&[0xfe, 0x80, 0,0,0,0,0,0,0,0,0,0,0,0,0,1]); //source
+        bytes.extend_from_slice(&[0xfe, 0x80, 0,0,0,0,0,0,0,0,0,0,0,0,0,2]); //destination
+        bytes.extend_from_slice(&fragment);
+        bytes.extend_from_slice(&fragment_payload);
+
+        let slice = PacketSlice::from_ip(&bytes).unwrap();
+        let ip = slice.ipv6().unwrap();
+        assert_eq!(ip.next_header(), IPV6_FRAG);
+        assert!(slice.transport().is_none());
+        assert_eq!(slice.payload(), &fragment_payload);
+    }
+
+    #[test]
+    fn from_ip_errors_on_short_slice() {
+        assert_eq!(
+            PacketSlice::from_ip(&[]).unwrap_err(),
+            ReadError::UnexpectedEndOfSlice(1)
+        );
+    }
+}
@@ -0,0 +1,368 @@
+use super::*;
+
+///Dispatch byte values (RFC 6282 / RFC 4944) identifying what kind of
+///6LoWPAN payload follows the IEEE 802.15.4 MAC header.
+mod dispatch {
+    ///`011xxxxx` - LOWPAN_IPHC compressed IPv6 header.
+    pub const IPHC_MASK: u8 = 0b1110_0000;
+    pub const IPHC_PATTERN: u8 = 0b0110_0000;
+    ///`11000xxx` - first fragment of a fragmented datagram.
+    pub const FRAG1: u8 = 0b1100_0000;
+    ///`11100xxx` - subsequent fragment of a fragmented datagram.
+    pub const FRAGN: u8 = 0b1110_0000;
+    pub const FRAG_MASK: u8 = 0b1111_1000;
+}
+
+///Errors that can occur while decompressing a 6LoWPAN IPHC header.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SixLowPanError {
+    UnexpectedEndOfSlice(usize),
+    ///The packet is not a LOWPAN_IPHC encoded IPv6 header at all.
+    NotIphc,
+    ///Context based address compression (SAC/DAC = 1) needs a context table
+    ///that is not modeled by this decoder.
+    ContextBasedCompressionNotSupported,
+    ///Multicast address compression is not modeled by this decoder.
+    MulticastCompressionNotSupported,
+    ///Next Header Compression (NH = 1) is not modeled by this decoder; the
+    ///packet's next header field could not be recovered.
+    NextHeaderCompressionNotSupported,
+    ///Elided address without a usable link layer address to derive it from.
+    MissingLinkLayerAddress,
+}
+
+impl From<SixLowPanError> for ReadError {
+    fn from(value: SixLowPanError) -> ReadError {
+        match value {
+            SixLowPanError::UnexpectedEndOfSlice(min_size) => ReadError::UnexpectedEndOfSlice(min_size),
+            other => ReadError::SixLowPan(other),
+        }
+    }
+}
+
+///The first fragment of a fragmented 6LoWPAN datagram (RFC 4944 section 5.3).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SixLowPanFrag1Header {
+    ///Size, in octets, of the entire (uncompressed) IPv6 datagram this fragment belongs to.
+    pub datagram_size: u16,
+    ///Identifies which datagram the fragment belongs to.
+    pub datagram_tag: u16,
+}
+
+///A non-first fragment of a fragmented 6LoWPAN datagram (RFC 4944 section 5.3).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SixLowPanFragNHeader {
+    pub datagram_size: u16,
+    pub datagram_tag: u16,
+    ///Offset, in 8-octet units, of this fragment's payload within the datagram.
+    pub datagram_offset: u8,
+}
+
+///Either kind of 6LoWPAN fragmentation header.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SixLowPanFragHeader {
+    First(SixLowPanFrag1Header),
+    Subsequent(SixLowPanFragNHeader),
+}
+
+impl SixLowPanFragHeader {
+    ///Tries to read a FRAG1/FRAGN header. Returns `Ok(None)` if `slice`
+    ///does not start with a fragmentation dispatch byte.
+    pub fn read_from_slice(slice: &[u8]) -> Result<Option<(SixLowPanFragHeader, &[u8])>, SixLowPanError> {
+        if slice.is_empty() {
+            return Err(SixLowPanError::UnexpectedEndOfSlice(1));
+        }
+        let masked = slice[0] & dispatch::FRAG_MASK;
+        if masked != dispatch::FRAG1 && masked != dispatch::FRAGN {
+            return Ok(None);
+        }
+        if slice.len() < 4 {
+            return Err(SixLowPanError::UnexpectedEndOfSlice(4));
+        }
+        let datagram_size = (u16::from(slice[0] & 0b0000_0111) << 8) | u16::from(slice[1]);
+        let datagram_tag = u16::from_be_bytes([slice[2], slice[3]]);
+
+        if masked == dispatch::FRAG1 {
+            Ok(Some((
+                SixLowPanFragHeader::First(SixLowPanFrag1Header{ datagram_size, datagram_tag }),
+                &slice[4..],
+            )))
+        } else {
+            if slice.len() < 5 {
+                return Err(SixLowPanError::UnexpectedEndOfSlice(5));
+            }
+            Ok(Some((
+                SixLowPanFragHeader::Subsequent(SixLowPanFragNHeader{
+                    datagram_size,
+                    datagram_tag,
+                    datagram_offset: slice[4],
+                }),
+                &slice[5..],
+            )))
+        }
+    }
+}
+
+///2 bit Traffic-Class/Flow-Label compression field of LOWPAN_IPHC.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum TrafficClassFlowLabel { Both, FlowLabelOnly, TrafficClassOnly, Elided }
+
+///2 bit Hop Limit compression field of LOWPAN_IPHC.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum HopLimit { Inline, One, SixtyFour, TwoFiveFive }
+
+///Decompresses a 6LoWPAN LOWPAN_IPHC (RFC 6282) header back into a regular
+///`Ipv6Header` plus the next-header value & remaining payload. Context based
+///address compression and multicast address compression are not supported,
+///since they require state (a context table) this crate does not keep.
+pub fn decompress_iphc<'a>(
+    slice: &'a [u8],
+    link_src: Ieee802154Address,
+    link_dest: Ieee802154Address,
+) -> Result<(Ipv6Header, u8, &'a [u8]), SixLowPanError> {
+    if slice.len() < 2 {
+        return Err(SixLowPanError::UnexpectedEndOfSlice(2));
+    }
+    if (slice[0] & dispatch::IPHC_MASK) != dispatch::IPHC_PATTERN {
+        return Err(SixLowPanError::NotIphc);
+    }
+
+    let tf = match (slice[0] >> 3) & 0b11 {
+        0b00 => TrafficClassFlowLabel::Both,
+        0b01 => TrafficClassFlowLabel::FlowLabelOnly,
+        0b10 => TrafficClassFlowLabel::TrafficClassOnly,
+        _ => TrafficClassFlowLabel::Elided,
+    };
+    let nh_compressed = 0 != (slice[0] >> 2) & 0b1;
+    let hlim = match slice[0] & 0b11 {
+        0b00 => HopLimit::Inline,
+        0b01 => HopLimit::One,
+        0b10 => HopLimit::SixtyFour,
+        _ => HopLimit::TwoFiveFive,
+    };
+
+    let cid = 0 != (slice[1] >> 7) & 0b1;
+    let sac = 0 != (slice[1] >> 6) & 0b1;
+    let sam = (slice[1] >> 4) & 0b11;
+    let m = 0 != (slice[1] >> 3) & 0b1;
+    let dac = 0 != (slice[1] >> 2) & 0b1;
+    let dam = slice[1] & 0b11;
+
+    if sac || dac {
+        return Err(SixLowPanError::ContextBasedCompressionNotSupported);
+    }
+    if m {
+        return Err(SixLowPanError::MulticastCompressionNotSupported);
+    }
+
+    let mut rest = &slice[2..];
+    //the (unused here) Context Identifier Extension byte
+    if cid {
+        if rest.is_empty() {
+            return Err(SixLowPanError::UnexpectedEndOfSlice(1));
+        }
+        rest = &rest[1..];
+    }
+
+    let (traffic_class, flow_label) = match tf {
+        TrafficClassFlowLabel::Both => {
+            let bytes = take(&mut rest, 4)?;
+            (decompress_traffic_class(bytes[0]), u32::from_be_bytes([0, bytes[1] & 0b1111, bytes[2], bytes[3]]))
+        },
+        TrafficClassFlowLabel::FlowLabelOnly => {
+            let bytes = take(&mut rest, 3)?;
+            //DSCP is elided (= 0), only the ECN bits (top 2 bits of the byte) survive
+            (decompress_traffic_class(bytes[0] & 0b1100_0000), u32::from_be_bytes([0, bytes[0] & 0b1111, bytes[1], bytes[2]]))
+        },
+        TrafficClassFlowLabel::TrafficClassOnly => {
+            let bytes = take(&mut rest, 1)?;
+            (decompress_traffic_class(bytes[0]), 0)
+        },
+        TrafficClassFlowLabel::Elided => (0, 0),
+    };
+
+    let next_header = if nh_compressed {
+        return Err(SixLowPanError::NextHeaderCompressionNotSupported);
+    } else {
+        take(&mut rest, 1)?[0]
+    };
+
+    let hop_limit = match hlim {
+        HopLimit::Inline => take(&mut rest, 1)?[0],
+        HopLimit::One => 1,
+        HopLimit::SixtyFour => 64,
+        HopLimit::TwoFiveFive => 255,
+    };
+
+    let source = decompress_address(&mut rest, sam, link_src)?;
+    let destination = decompress_address(&mut rest, dam, link_dest)?;
+
+    Ok((
+        Ipv6Header{
+            traffic_class,
+            flow_label,
+            payload_length: 0, //not transmitted, filled in by the caller once the payload is known
+            next_header,
+            hop_limit,
+            source,
+            destination,
+        },
+        next_header,
+        rest,
+    ))
+}
+
+///The compressed Traffic Class byte carries ECN/DSCP in the opposite bit
+///order from the real IPv6 Traffic Class octet (RFC 6282 section 3.1.1):
+///compressed is `ECN(2) | DSCP(6)`, but the IPv6 header wants `DSCP(6) | ECN(2)`.
+fn decompress_traffic_class(compressed: u8) -> u8 {
+    let ecn = compressed >> 6;
+    let dscp = compressed & 0b0011_1111;
+    (dscp << 2) | ecn
+}
+
+fn take<'a>(rest: &mut &'a [u8], len: usize) -> Result<&'a [u8], SixLowPanError> {
+    if rest.len() < len {
+        return Err(SixLowPanError::UnexpectedEndOfSlice(len));
+    }
+    let (result, new_rest) = rest.split_at(len);
+    *rest = new_rest;
+    Ok(result)
+}
+
+///Reconstructs a full 128 bit IPv6 address from its (partially) elided
+///SAM/DAM encoded form, filling elided bytes in from the IEEE 802.15.4
+///link layer address as described in RFC 6282 section 3.2.2.
+fn decompress_address(rest: &mut &[u8], mode: u8, link_addr: Ieee802154Address) -> Result<[u8;16], SixLowPanError> {
+    match mode {
+        0b00 => {
+            let bytes = take(rest, 16)?;
+            let mut addr = [0u8;16];
+            addr.copy_from_slice(bytes);
+            Ok(addr)
+        },
+        0b01 => {
+            let bytes = take(rest, 8)?;
+            let mut addr = [0u8;16];
+            addr[0] = 0xfe;
+            addr[1] = 0x80;
+            addr[8..16].copy_from_slice(bytes);
+            Ok(addr)
+        },
+        0b10 => {
+            let bytes = take(rest, 2)?;
+            let mut addr = [0u8;16];
+            addr[0] = 0xfe;
+            addr[1] = 0x80;
+            addr[11] = 0xff;
+            addr[12] = 0xfe;
+            addr[14] = bytes[0];
+            addr[15] = bytes[1];
+            Ok(addr)
+        },
+        _ => {
+            //fully elided -> derive the interface identifier from the link layer address
+            let mut addr = [0u8;16];
+            addr[0] = 0xfe;
+            addr[1] = 0x80;
+            match link_addr {
+                Ieee802154Address::Extended(mac) => {
+                    addr[8..16].copy_from_slice(&mac);
+                    //invert the universal/local bit, as required for the modified EUI-64 form
+                    addr[8] ^= 0b0000_0010;
+                },
+                Ieee802154Address::Short(short) => {
+                    addr[11] = 0xff;
+                    addr[12] = 0xfe;
+                    addr[14] = short[0];
+                    addr[15] = short[1];
+                },
+                Ieee802154Address::None => return Err(SixLowPanError::MissingLinkLayerAddress),
+            }
+            Ok(addr)
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn traffic_class_ecn_dscp_are_swapped() {
+        //compressed: ECN = 0b10, DSCP = 0b000001
+        let compressed = (0b10 << 6) | 0b0000_0001;
+        //real IPv6 traffic class: DSCP high 6 bits, ECN low 2 bits
+        assert_eq!(decompress_traffic_class(compressed), (0b0000_0001 << 2) | 0b10);
+    }
+
+    #[test]
+    fn decompresses_fully_elided_header_using_link_addresses() {
+        //TF = 11 (elided), NH = 0 (inline), HLIM = 11 (255)
+        let byte0 = 0b011_11_0_11;
+        //CID=0, SAC=0, SAM=11 (elided), M=0, DAC=0, DAM=11 (elided)
+        let byte1 = 0b0_0_11_0_0_11;
+        let next_header = 17; //UDP
+        let payload = [0xde, 0xad];
+
+        let mut slice = vec![byte0, byte1, next_header];
+        slice.extend_from_slice(&payload);
+
+        let link_src = Ieee802154Address::Extended([0x02, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77]);
+        let link_dest = Ieee802154Address::Extended([0x10, 0x20, 0x30, 0x40, 0x50, 0x60, 0x70, 0x80]);
+
+        let (ip, nh, rest) = decompress_iphc(&slice, link_src, link_dest).unwrap();
+        assert_eq!(ip.traffic_class, 0);
+        assert_eq!(ip.flow_label, 0);
+        assert_eq!(ip.hop_limit, 255);
+        assert_eq!(nh, 17);
+        assert_eq!(ip.next_header, 17);
+        assert_eq!(rest, &payload);
+
+        assert_eq!(&ip.source[0..2], &[0xfe, 0x80]);
+        assert_eq!(ip.source[8], 0x02 ^ 0b10);
+        assert_eq!(&ip.source[9..16], &[0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77]);
+
+        assert_eq!(&ip.destination[0..2], &[0xfe, 0x80]);
+        assert_eq!(ip.destination[8], 0x10 ^ 0b10);
+    }
+
+    #[test]
+    fn rejects_context_based_compression() {
+        //SAC = 1
+        let byte0 = 0b011_11_0_11;
+        let byte1 = 0b0_1_11_0_0_11;
+        let slice = [byte0, byte1, 17];
+        assert_eq!(
+            decompress_iphc(&slice, Ieee802154Address::None, Ieee802154Address::None).unwrap_err(),
+            SixLowPanError::ContextBasedCompressionNotSupported
+        );
+    }
+
+    #[test]
+    fn reads_frag1_and_fragn_headers() {
+        //FRAG1: dispatch 11000, datagram_size = 0x123, datagram_tag = 0xabcd
+        let frag1 = [0b1100_0001, 0x23, 0xab, 0xcd];
+        let (header, rest) = SixLowPanFragHeader::read_from_slice(&frag1).unwrap().unwrap();
+        assert_eq!(
+            header,
+            SixLowPanFragHeader::First(SixLowPanFrag1Header{ datagram_size: 0x123, datagram_tag: 0xabcd })
+        );
+        assert!(rest.is_empty());
+
+        //FRAGN: dispatch 11100, same size/tag, offset = 5
+        let fragn = [0b1110_0001, 0x23, 0xab, 0xcd, 5];
+        let (header, rest) = SixLowPanFragHeader::read_from_slice(&fragn).unwrap().unwrap();
+        assert_eq!(
+            header,
+            SixLowPanFragHeader::Subsequent(SixLowPanFragNHeader{ datagram_size: 0x123, datagram_tag: 0xabcd, datagram_offset: 5 })
+        );
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn non_fragment_dispatch_returns_none() {
+        let slice = [0b0110_0000, 0, 17];
+        assert_eq!(SixLowPanFragHeader::read_from_slice(&slice).unwrap(), None);
+    }
+}
@@ -0,0 +1,24 @@
+use super::*;
+
+///The possible headers appearing after the ip header that `PacketHeaders`
+///currently knows how to decode.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TransportHeader {
+    Udp(UdpHeader),
+    Tcp(TcpHeader),
+    Icmpv4(Icmpv4Header),
+    Icmpv6(Icmpv6Header),
+}
+
+impl TransportHeader {
+    ///Returns the size of the underlying transport header in bytes.
+    pub fn header_len(&self) -> usize {
+        use TransportHeader::*;
+        match self {
+            Udp(_) => UdpHeader::SERIALIZED_SIZE,
+            Tcp(header) => header.header_len() as usize,
+            Icmpv4(_) => ICMPV4_HEADER_LEN,
+            Icmpv6(_) => ICMPV6_HEADER_LEN,
+        }
+    }
+}